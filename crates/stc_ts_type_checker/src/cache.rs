@@ -0,0 +1,166 @@
+//! Persistent, content-addressed cache of checked modules.
+//!
+//! `Checker::check` used to re-analyze every module on every run, which
+//! makes large projects (rxjs, vite, redux, ...) slow to re-check when only
+//! a handful of files actually changed. `DiskCache` stores the result of
+//! analyzing a module (its generated `.d.ts` and diagnostics) under a key
+//! derived from the module's own source plus the fingerprints of its
+//! transitive imports, mirroring Deno's `DiskCache` + checksum approach.
+//! Entries are additionally namespaced by [`CACHE_FORMAT_VERSION`], so a
+//! change to what gets cached doesn't risk an old entry being silently
+//! deserialized and treated as valid under a new format.
+
+use std::{
+    fs,
+    io::{self, ErrorKind as IoErrorKind},
+    path::PathBuf,
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use swc_ecma_ast::Module;
+
+use crate::{coverage::CoverageSpan, Error};
+
+/// Bump whenever `CachedModule`'s shape changes in a way that isn't
+/// guaranteed to fail to deserialize on its own (a renamed-but-still-typed
+/// field, a reordered enum variant, ...). Entries are sharded under a
+/// directory named for this version, so a bump makes every entry from an
+/// older version simply unreachable instead of being deserialized as if it
+/// were still valid.
+const CACHE_FORMAT_VERSION: u8 = 1;
+
+/// The cached result of checking a single module.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedModule {
+    pub dts: Module,
+    pub errors: Vec<Error>,
+    pub coverage: Vec<CoverageSpan>,
+}
+
+/// Computes the fingerprint of a module from its own source text and the
+/// fingerprints of the modules it imports.
+///
+/// Folding in the dependency fingerprints means a module's cache entry is
+/// invalidated whenever anything it depends on (transitively) changes, even
+/// though its own source text is untouched.
+pub fn fingerprint(source: &str, import_fingerprints: &[String]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(source.as_bytes());
+    for fp in import_fingerprints {
+        hasher.update(fp.as_bytes());
+    }
+    hex::encode(hasher.finalize())
+}
+
+/// A directory of cached [`CachedModule`]s, keyed by [`fingerprint`].
+pub struct DiskCache {
+    root: PathBuf,
+}
+
+impl DiskCache {
+    pub fn new(root: PathBuf) -> io::Result<Self> {
+        fs::create_dir_all(&root)?;
+        Ok(DiskCache { root })
+    }
+
+    /// Shards entries into `root/v<CACHE_FORMAT_VERSION>/<first two hex
+    /// chars>/<rest>` so a single directory never ends up with one entry per
+    /// module in the project, and so a format version bump starts every
+    /// entry fresh rather than risking a stale one being read back as valid.
+    fn path_for(&self, fingerprint: &str) -> PathBuf {
+        let (shard, rest) = fingerprint.split_at(2);
+        self.root.join(format!("v{}", CACHE_FORMAT_VERSION)).join(shard).join(rest)
+    }
+
+    pub fn get(&self, fingerprint: &str) -> Option<CachedModule> {
+        let bytes = match fs::read(self.path_for(fingerprint)) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == IoErrorKind::NotFound => return None,
+            Err(_) => return None,
+        };
+
+        serde_json::from_slice(&bytes).ok()
+    }
+
+    pub fn set(&self, fingerprint: &str, entry: &CachedModule) -> io::Result<()> {
+        let path = self.path_for(fingerprint);
+        fs::create_dir_all(path.parent().unwrap())?;
+
+        let bytes = serde_json::to_vec(entry).expect("CachedModule is always serializable");
+        fs::write(path, bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("stc-cache-test-{}-{}-{}", std::process::id(), name, CACHE_FORMAT_VERSION));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn empty_module() -> CachedModule {
+        CachedModule {
+            dts: Module {
+                span: swc_common::DUMMY_SP,
+                body: Vec::new(),
+                shebang: None,
+            },
+            errors: Vec::new(),
+            coverage: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn fingerprint_is_stable_for_the_same_input() {
+        let a = fingerprint("export const a = 1;", &["dep1".to_string()]);
+        let b = fingerprint("export const a = 1;", &["dep1".to_string()]);
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn fingerprint_changes_with_source() {
+        let a = fingerprint("export const a = 1;", &[]);
+        let b = fingerprint("export const a = 2;", &[]);
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn fingerprint_changes_with_a_dependencys_fingerprint() {
+        let a = fingerprint("export const a = 1;", &["dep1".to_string()]);
+        let b = fingerprint("export const a = 1;", &["dep2".to_string()]);
+        assert_ne!(a, b, "a change to an imported module's fingerprint must invalidate its importers");
+    }
+
+    #[test]
+    fn round_trips_through_disk() {
+        let cache = DiskCache::new(test_dir("round-trip")).unwrap();
+        let fp = fingerprint("export const a = 1;", &[]);
+
+        assert!(cache.get(&fp).is_none());
+
+        cache.set(&fp, &empty_module()).unwrap();
+        assert!(cache.get(&fp).is_some());
+    }
+
+    #[test]
+    fn bumping_the_format_version_makes_old_entries_unreachable() {
+        let dir = test_dir("version-bump");
+        let cache = DiskCache::new(dir.clone()).unwrap();
+        let fp = fingerprint("export const a = 1;", &[]);
+        cache.set(&fp, &empty_module()).unwrap();
+
+        // Simulates a format bump: the entry written under the real
+        // `CACHE_FORMAT_VERSION` directory is invisible once the version
+        // this cache shards under changes, instead of being deserialized
+        // as if it were still valid.
+        let old_shard = dir.join(format!("v{}", CACHE_FORMAT_VERSION));
+        let new_shard = dir.join(format!("v{}", CACHE_FORMAT_VERSION as u16 + 1));
+        fs::rename(&old_shard, &new_shard).unwrap();
+
+        assert!(cache.get(&fp).is_none());
+    }
+}
@@ -0,0 +1,520 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet, VecDeque},
+    fs::read_to_string,
+    path::PathBuf,
+    sync::{Arc, Condvar, Mutex},
+    thread::{self, ThreadId},
+};
+
+use stc_ts_env::Env;
+use stc_ts_module_loader::resolvers::Resolve;
+use swc_common::{errors::Handler, FileName, SourceMap};
+use swc_ecma_ast::Module;
+use swc_ecma_parser::{lexer::Lexer, Parser, StringInput, Syntax, TsConfig};
+
+use crate::{
+    cache::{self, CachedModule, DiskCache},
+    Error,
+};
+
+/// Opaque handle identifying a module tracked by a [`Checker`].
+///
+/// Ids are assigned in the order modules are first seen by [`Checker::id`]
+/// and are stable for the lifetime of the `Checker`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ModuleId(u32);
+
+/// Where a module is in the check lifecycle.
+///
+/// Tracked separately from `dts` because a module with a cyclic import
+/// needs to be distinguishable from one that simply hasn't been looked at
+/// yet: re-entering `check()` for a module that's already `InProgress`
+/// means an import cycle, not a cache miss.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ModuleStatus {
+    NotStarted,
+    InProgress,
+    Done,
+}
+
+/// Per-module state kept around after a module has been checked.
+struct ModuleData {
+    path: Arc<FileName>,
+    status: ModuleStatus,
+    dts: Option<Module>,
+    /// Content hash of this module's source plus its transitive imports',
+    /// used both as the [`DiskCache`] key and to let dependents fold this
+    /// module's identity into their own fingerprint.
+    fingerprint: Option<String>,
+    /// Modules that import this one directly, so a change here can be
+    /// propagated to everything that needs to be rechecked. See
+    /// [`Checker::affected_by`].
+    dependents: HashSet<ModuleId>,
+    /// Type positions recorded while this module was analyzed, for
+    /// [`Checker::coverage`]. Unlike `dts`, this covers expressions inside
+    /// function/method bodies, not just public signatures.
+    coverage: Vec<crate::coverage::CoverageSpan>,
+}
+
+thread_local! {
+    /// Ids this thread is currently partway through `check()`-ing, i.e. the
+    /// current import chain. Used to detect cycles (`a` imports `b` imports
+    /// `a`) within a single thread without recursing forever.
+    static CHECKING: RefCell<HashSet<ModuleId>> = RefCell::new(HashSet::new());
+}
+
+/// Entry point of the type checker.
+///
+/// `Checker` owns the [`Env`] (builtin libs + module registry), drives
+/// module resolution through a [`Resolve`]r, and accumulates diagnostics
+/// that can be drained with [`Checker::take_errors`].
+pub struct Checker {
+    cm: Arc<SourceMap>,
+    handler: Arc<Handler>,
+    env: Env,
+    ts_config: TsConfig,
+    cache: Option<DiskCache>,
+    resolver: Arc<dyn Resolve>,
+
+    ids: Mutex<HashMap<Arc<FileName>, ModuleId>>,
+    modules: Mutex<Vec<ModuleData>>,
+    /// Signaled whenever any module's `status` changes, so a thread that
+    /// finds a module `InProgress` on another thread can wait for it to
+    /// become `Done` instead of re-checking it.
+    status_changed: Condvar,
+    /// Which thread currently owns (is actively running `check_uncached`
+    /// for) each `InProgress` module. Needed, alongside `waiting_for`, to
+    /// detect import cycles that cross threads: `CHECKING` alone only sees
+    /// cycles within a single thread's own call stack.
+    owners: Mutex<HashMap<ModuleId, ThreadId>>,
+    /// Which module each thread is currently blocked waiting on, if any.
+    /// Following `waiting_for` from the owner of a module a thread is about
+    /// to wait on is how [`Checker::would_deadlock`] finds cross-thread
+    /// cycles before they turn into an actual deadlock.
+    waiting_for: Mutex<HashMap<ThreadId, ModuleId>>,
+    errors: Mutex<Vec<Error>>,
+}
+
+impl Checker {
+    pub fn new(
+        cm: Arc<SourceMap>,
+        handler: Arc<Handler>,
+        env: Env,
+        ts_config: TsConfig,
+        cache_dir: Option<PathBuf>,
+        resolver: Arc<dyn Resolve>,
+    ) -> Self {
+        // A cache directory that can't be created just means we run without
+        // a cache; it's never fatal to the check itself.
+        let cache = cache_dir.and_then(|dir| DiskCache::new(dir).ok());
+
+        Checker {
+            cm,
+            handler,
+            env,
+            ts_config,
+            cache,
+            resolver,
+            ids: Default::default(),
+            modules: Default::default(),
+            status_changed: Condvar::new(),
+            owners: Default::default(),
+            waiting_for: Default::default(),
+            errors: Default::default(),
+        }
+    }
+
+    pub fn cm(&self) -> &Arc<SourceMap> {
+        &self.cm
+    }
+
+    pub fn handler(&self) -> &Arc<Handler> {
+        &self.handler
+    }
+
+    /// Returns the [`ModuleId`] for `path`, assigning a new one if this is
+    /// the first time `path` is seen.
+    pub fn id(&self, path: &Arc<FileName>) -> ModuleId {
+        let mut ids = self.ids.lock().unwrap();
+        if let Some(id) = ids.get(path) {
+            return *id;
+        }
+
+        let mut modules = self.modules.lock().unwrap();
+        let id = ModuleId(modules.len() as u32);
+        modules.push(ModuleData {
+            path: path.clone(),
+            status: ModuleStatus::NotStarted,
+            dts: None,
+            fingerprint: None,
+            dependents: Default::default(),
+            coverage: Default::default(),
+        });
+        ids.insert(path.clone(), id);
+        id
+    }
+
+    /// Like [`Checker::id`], but never assigns a new id: returns `None` if
+    /// `path` hasn't been seen yet.
+    pub fn existing_id(&self, path: &Arc<FileName>) -> Option<ModuleId> {
+        self.ids.lock().unwrap().get(path).copied()
+    }
+
+    /// The path a given [`ModuleId`] was first checked under.
+    pub fn path_of(&self, id: ModuleId) -> Arc<FileName> {
+        self.modules.lock().unwrap()[id.0 as usize].path.clone()
+    }
+
+    /// Modules that directly import `id`.
+    pub fn dependents_of(&self, id: ModuleId) -> Vec<ModuleId> {
+        self.modules.lock().unwrap()[id.0 as usize].dependents.iter().copied().collect()
+    }
+
+    /// The transitive closure of modules affected by a change to any module
+    /// in `changed`: `changed` itself plus everything that (transitively)
+    /// depends on it.
+    pub fn affected_by(&self, changed: &[ModuleId]) -> HashSet<ModuleId> {
+        let mut affected: HashSet<ModuleId> = changed.iter().copied().collect();
+        let mut queue: VecDeque<ModuleId> = changed.iter().copied().collect();
+
+        while let Some(id) = queue.pop_front() {
+            for dependent in self.dependents_of(id) {
+                if affected.insert(dependent) {
+                    queue.push_back(dependent);
+                }
+            }
+        }
+
+        affected
+    }
+
+    /// Forgets the stored check result for `id` (its status, `dts`,
+    /// fingerprint and coverage), so the next [`Checker::check`] call for it
+    /// re-analyzes from scratch instead of short-circuiting on a stale
+    /// `dts`. Its path and recorded dependents are kept.
+    pub fn invalidate(&self, id: ModuleId) {
+        let mut modules = self.modules.lock().unwrap();
+        modules[id.0 as usize].status = ModuleStatus::NotStarted;
+        modules[id.0 as usize].dts = None;
+        modules[id.0 as usize].fingerprint = None;
+        modules[id.0 as usize].coverage.clear();
+    }
+
+    /// Checks `entry` (and everything it transitively imports), recording
+    /// diagnostics and the generated `.d.ts` module for later retrieval.
+    pub fn check(&self, entry: Arc<FileName>) -> ModuleId {
+        let id = self.id(&entry);
+        let this_thread = thread::current().id();
+
+        // An import cycle (`a` imports `b` imports `a`) re-enters `check`
+        // for a module that's still being checked further up this same
+        // thread's call stack. Recursing again would never terminate, so
+        // just report it as checked-for-now; the outer call will finish
+        // filling in its `dts` once the cycle unwinds.
+        if CHECKING.with(|c| c.borrow().contains(&id)) {
+            return id;
+        }
+
+        {
+            let mut modules = self.modules.lock().unwrap();
+            loop {
+                match modules[id.0 as usize].status {
+                    ModuleStatus::Done => return id,
+                    ModuleStatus::InProgress => {
+                        // The cycle above only catches this thread re-entering
+                        // a module it already owns. Two entries checked in
+                        // parallel can each own one half of a cycle on a
+                        // *different* thread (thread 1 owns `a`, thread 2
+                        // owns `b`, `a` imports `b` imports `a`), in which
+                        // case neither thread's `CHECKING` set contains the
+                        // other's module and both would block on each other
+                        // forever. Detect that before waiting: if the chain
+                        // of "who owns what I'd wait on, and what are *they*
+                        // waiting on" loops back to a module we own, nothing
+                        // is gained by blocking, so bail out like the
+                        // same-thread case instead of deadlocking.
+                        if self.would_deadlock(this_thread, id) {
+                            return id;
+                        }
+
+                        self.waiting_for.lock().unwrap().insert(this_thread, id);
+                        modules = self.status_changed.wait(modules).unwrap();
+                        self.waiting_for.lock().unwrap().remove(&this_thread);
+                    }
+                    ModuleStatus::NotStarted => {
+                        modules[id.0 as usize].status = ModuleStatus::InProgress;
+                        self.owners.lock().unwrap().insert(id, this_thread);
+                        break;
+                    }
+                }
+            }
+        }
+
+        CHECKING.with(|c| c.borrow_mut().insert(id));
+        let result = self.check_uncached(id, &entry);
+        CHECKING.with(|c| c.borrow_mut().remove(&id));
+
+        self.owners.lock().unwrap().remove(&id);
+        self.modules.lock().unwrap()[id.0 as usize].status = ModuleStatus::Done;
+        self.status_changed.notify_all();
+
+        result
+    }
+
+    /// Whether `thread` waiting on `target` (currently `InProgress`) would
+    /// deadlock: true if following "who owns `target`, what are they
+    /// waiting on, who owns *that*, ..." ever leads back to a module
+    /// `thread` itself owns, meaning everyone in the chain is stuck waiting
+    /// on each other.
+    fn would_deadlock(&self, thread: ThreadId, target: ModuleId) -> bool {
+        let owners = self.owners.lock().unwrap();
+        let waiting_for = self.waiting_for.lock().unwrap();
+
+        let mut owner = match owners.get(&target) {
+            Some(&owner) => owner,
+            None => return false,
+        };
+        let mut seen = HashSet::new();
+
+        loop {
+            if owner == thread {
+                return true;
+            }
+            if !seen.insert(owner) {
+                return false;
+            }
+
+            let waited_on = match waiting_for.get(&owner) {
+                Some(&id) => id,
+                None => return false,
+            };
+            owner = match owners.get(&waited_on) {
+                Some(&owner) => owner,
+                None => return false,
+            };
+        }
+    }
+
+    fn check_uncached(&self, id: ModuleId, entry: &Arc<FileName>) -> ModuleId {
+        let path = match &**entry {
+            FileName::Real(path) => path.clone(),
+            _ => return id,
+        };
+
+        let src = match read_to_string(&path) {
+            Ok(src) => src,
+            Err(_) => {
+                self.errors.lock().unwrap().push(Error {
+                    span: swc_common::DUMMY_SP,
+                    kind: crate::ErrorKind::ModuleNotFound {
+                        specifier: path.display().to_string(),
+                    },
+                });
+                return id;
+            }
+        };
+
+        let fm = self.cm.new_source_file(FileName::Real(path.clone()), src);
+
+        let lexer = Lexer::new(Syntax::Typescript(self.ts_config), Default::default(), StringInput::from(&*fm), None);
+        let mut parser = Parser::new_from(lexer);
+
+        let module = match parser.parse_module() {
+            Ok(module) => module,
+            Err(_) => return id,
+        };
+
+        let mut dep_fingerprints = Vec::new();
+        // A dependency that's still `InProgress` (we're partway round an
+        // import cycle through it) doesn't have a `fingerprint` yet, so its
+        // contribution can't be folded into ours. Rather than silently
+        // hashing as if that dependency didn't exist - which would leave the
+        // disk cache unable to tell a real edit to it from no change at all -
+        // treat participating in a cycle as cache-busting: this module's own
+        // `fingerprint` is left `None`, which both skips the disk cache for
+        // it and, by the same rule, propagates to anything that imports it.
+        let mut in_cycle = false;
+        for dep in self.imports_of(&module, &path) {
+            let dep_id = self.check(Arc::new(FileName::Real(dep)));
+            self.modules.lock().unwrap()[dep_id.0 as usize].dependents.insert(id);
+            match &self.modules.lock().unwrap()[dep_id.0 as usize].fingerprint {
+                Some(fp) => dep_fingerprints.push(fp.clone()),
+                None => in_cycle = true,
+            }
+        }
+
+        let fingerprint = cache::fingerprint(&fm.src, &dep_fingerprints);
+        let cache = self.cache.as_ref().filter(|_| !in_cycle);
+
+        if let Some(cache) = cache {
+            if let Some(cached) = cache.get(&fingerprint) {
+                self.errors.lock().unwrap().extend(cached.errors);
+                let mut modules = self.modules.lock().unwrap();
+                modules[id.0 as usize].dts = Some(cached.dts);
+                modules[id.0 as usize].fingerprint = Some(fingerprint);
+                modules[id.0 as usize].coverage = cached.coverage;
+                return id;
+            }
+        }
+
+        // The actual type analysis (binding resolution, inference, widening,
+        // ...) lives in `stc_ts_file_analyzer`; this crate only orchestrates
+        // module loading and collects the results it returns. `coverage` is
+        // recorded per expression/declaration as the module is analyzed, so
+        // it reflects resolved types inside function bodies, not just the
+        // signatures that make it into `dts`.
+        let (dts, errors, coverage) = stc_ts_file_analyzer::analyze_module(&self.env, &module);
+
+        if let Some(cache) = cache {
+            let _ = cache.set(&fingerprint, &CachedModule {
+                dts: dts.clone(),
+                errors: errors.clone(),
+                coverage: coverage.clone(),
+            });
+        }
+
+        self.errors.lock().unwrap().extend(errors);
+
+        let mut modules = self.modules.lock().unwrap();
+        modules[id.0 as usize].dts = Some(dts);
+        modules[id.0 as usize].fingerprint = if in_cycle { None } else { Some(fingerprint) };
+        modules[id.0 as usize].coverage = coverage;
+
+        id
+    }
+
+    /// Resolves the module specifiers imported by `module` to file paths,
+    /// relative to `base`.
+    fn imports_of(&self, module: &Module, base: &std::path::Path) -> Vec<PathBuf> {
+        use swc_ecma_ast::{ModuleDecl, ModuleItem};
+
+        module
+            .body
+            .iter()
+            .filter_map(|item| match item {
+                ModuleItem::ModuleDecl(ModuleDecl::Import(import)) => Some(&*import.src.value),
+                _ => None,
+            })
+            .filter_map(|specifier| self.resolver.resolve(base, specifier).ok())
+            .collect()
+    }
+
+    /// Drains and returns every diagnostic collected so far.
+    pub fn take_errors(&self) -> Vec<Error> {
+        std::mem::take(&mut *self.errors.lock().unwrap())
+    }
+
+    /// Like [`Checker::take_errors`], but in the JSON-serializable shape
+    /// described in [`crate::diagnostics`].
+    pub fn take_diagnostics(&self) -> Vec<crate::diagnostics::Diagnostic> {
+        self.take_errors()
+            .iter()
+            .map(|err| crate::diagnostics::Diagnostic::from_error(err, &self.cm))
+            .collect()
+    }
+
+    /// Takes the generated `.d.ts` module for `id`, if it has been checked.
+    pub fn take_dts(&self, id: ModuleId) -> Option<Module> {
+        self.modules.lock().unwrap()[id.0 as usize].dts.take()
+    }
+
+    /// Type coverage for every module checked so far whose `.d.ts` is still
+    /// stored, in the shape described in [`crate::coverage`].
+    ///
+    /// Unlike [`Checker::take_dts`], this doesn't consume the stored
+    /// coverage spans, so it can be called more than once. A module whose
+    /// `.d.ts` has already been taken with [`Checker::take_dts`] is left
+    /// out of the result.
+    pub fn coverage(&self) -> Vec<crate::coverage::FileCoverage> {
+        self.modules
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|m| m.dts.is_some())
+            .map(|m| crate::coverage::coverage_of(m.path.to_string(), &m.coverage))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, thread};
+
+    use stc_ts_env::ModuleConfig;
+    use stc_ts_module_loader::resolvers::node::NodeResolver;
+    use swc_common::errors::ColorConfig;
+    use swc_ecma_ast::EsVersion;
+
+    use super::*;
+
+    fn checker() -> Checker {
+        let cm = Arc::new(SourceMap::default());
+        let handler = Arc::new(Handler::with_tty_emitter(ColorConfig::Never, true, false, Some(cm.clone())));
+
+        Checker::new(
+            cm,
+            handler,
+            Env::simple(Default::default(), EsVersion::latest(), ModuleConfig::None, &[]),
+            TsConfig::default(),
+            None,
+            Arc::new(NodeResolver),
+        )
+    }
+
+    /// Two modules that cyclically import each other, checked concurrently
+    /// on different threads, must not deadlock: thread 1 claims `a` while
+    /// thread 2 claims `b`, and neither's thread-local cycle set contains
+    /// the other's module, so only the cross-thread `would_deadlock` check
+    /// keeps them from blocking on each other forever.
+    #[test]
+    fn concurrent_checks_of_a_cross_thread_import_cycle_do_not_deadlock() {
+        let dir = std::env::temp_dir().join(format!("stc-checker-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let a_path = dir.join("a.ts");
+        let b_path = dir.join("b.ts");
+        fs::write(&a_path, "import './b';\nexport const a = 1;\n").unwrap();
+        fs::write(&b_path, "import './a';\nexport const b = 1;\n").unwrap();
+
+        let checker = Arc::new(checker());
+
+        let c1 = checker.clone();
+        let a = a_path.clone();
+        let t1 = thread::spawn(move || c1.check(Arc::new(FileName::Real(a))));
+
+        let c2 = checker.clone();
+        let b = b_path.clone();
+        let t2 = thread::spawn(move || c2.check(Arc::new(FileName::Real(b))));
+
+        // A regression here hangs this test instead of failing it cleanly;
+        // that's an acceptable tradeoff to exercise real cross-thread
+        // blocking, which a single-threaded test can't.
+        t1.join().unwrap();
+        t2.join().unwrap();
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn invalidate_clears_stored_state_so_check_reruns() {
+        let checker = checker();
+        let dir = std::env::temp_dir().join(format!("stc-checker-test-invalidate-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("a.ts");
+        fs::write(&path, "export const a = 1;\n").unwrap();
+
+        let id = checker.check(Arc::new(FileName::Real(path.clone())));
+        assert!(checker.take_dts(id).is_some());
+
+        checker.invalidate(id);
+        assert!(checker.modules.lock().unwrap()[id.0 as usize].dts.is_none());
+        assert_eq!(checker.modules.lock().unwrap()[id.0 as usize].status, ModuleStatus::NotStarted);
+
+        // Without the invalidation this would short-circuit on `Done` and
+        // leave `dts` empty, since it was already taken above.
+        assert_eq!(checker.check(Arc::new(FileName::Real(path))), id);
+        assert!(checker.take_dts(id).is_some());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,163 @@
+//! Type coverage reporting.
+//!
+//! Tallies how many type positions in a checked module resolved to a
+//! concrete type versus fell back to `any`, the same idea as Deno's
+//! `CoverageCollector` but over stc's own analysis instead of V8's runtime
+//! coverage.
+//!
+//! This used to walk the generated `.d.ts` with a [`Visit`](swc_ecma_visit)
+//! implementation, but a `.d.ts` only has declared parameter/return/property
+//! annotations: it has no function bodies, so an expression that silently
+//! widens to `any` inside one (`const y = x.foo()`) never shows up. Coverage
+//! is computed from [`CoverageSpan`]s instead, recorded by
+//! `stc_ts_file_analyzer` for every expression and declaration it resolves a
+//! type for while checking the module, not just the ones that end up in its
+//! public signature.
+
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+use swc_common::Span;
+
+/// One type position resolved while checking a module: an expression or
+/// declaration site along with whether its resolved type was a concrete one
+/// or `any`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CoverageSpan {
+    pub span: Span,
+    pub is_any: bool,
+}
+
+/// Coverage tally for a single file, in the shape used to emit a coverage
+/// report as JSON: `{ file, total, typed, anyCount, percent }`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileCoverage {
+    pub file: String,
+    pub total: usize,
+    pub typed: usize,
+    pub any_count: usize,
+}
+
+impl FileCoverage {
+    /// Percentage of type positions that resolved to something other than
+    /// `any`. A file with no type positions at all is reported as 100%
+    /// covered, since there's nothing left untyped.
+    pub fn percent(&self) -> f64 {
+        if self.total == 0 {
+            return 100.0;
+        }
+
+        (self.typed * 100) as f64 / self.total as f64
+    }
+}
+
+/// Hand-rolled rather than `#[derive(Serialize)]` because the report shape
+/// includes `percent`, which isn't a stored field but derived from `total`
+/// and `typed` - there's nothing for a derive to rename or skip its way to.
+impl Serialize for FileCoverage {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("FileCoverage", 5)?;
+        state.serialize_field("file", &self.file)?;
+        state.serialize_field("total", &self.total)?;
+        state.serialize_field("typed", &self.typed)?;
+        state.serialize_field("anyCount", &self.any_count)?;
+        state.serialize_field("percent", &self.percent())?;
+        state.end()
+    }
+}
+
+/// Computes [`FileCoverage`] for the file at `file`, labeling the result
+/// with `file`, from the [`CoverageSpan`]s recorded while it was checked.
+pub fn coverage_of(file: String, spans: &[CoverageSpan]) -> FileCoverage {
+    let any_count = spans.iter().filter(|s| s.is_any).count();
+
+    FileCoverage {
+        file,
+        total: spans.len(),
+        typed: spans.len() - any_count,
+        any_count,
+    }
+}
+
+/// The overall coverage percentage across every file in `report`, weighted
+/// by each file's number of type positions.
+pub fn overall_percent(report: &[FileCoverage]) -> f64 {
+    let total: usize = report.iter().map(|c| c.total).sum();
+    let typed: usize = report.iter().map(|c| c.typed).sum();
+
+    if total == 0 {
+        return 100.0;
+    }
+
+    typed as f64 / total as f64 * 100.0
+}
+
+/// Returns the files whose coverage falls below `threshold` (a percentage,
+/// e.g. `80.0`), so a caller can fail the run with a useful message instead
+/// of just a boolean.
+pub fn files_below_threshold(report: &[FileCoverage], threshold: f64) -> Vec<&FileCoverage> {
+    report.iter().filter(|c| c.percent() < threshold).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(is_any: bool) -> CoverageSpan {
+        CoverageSpan {
+            span: swc_common::DUMMY_SP,
+            is_any,
+        }
+    }
+
+    #[test]
+    fn coverage_of_tallies_any_vs_typed() {
+        let spans = vec![span(false), span(false), span(true)];
+        let coverage = coverage_of("foo.ts".to_string(), &spans);
+
+        assert_eq!(coverage.total, 3);
+        assert_eq!(coverage.typed, 2);
+        assert_eq!(coverage.any_count, 1);
+        assert!((coverage.percent() - 200.0 / 3.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn empty_file_is_fully_covered() {
+        let coverage = coverage_of("foo.ts".to_string(), &[]);
+        assert_eq!(coverage.percent(), 100.0);
+    }
+
+    #[test]
+    fn overall_percent_is_weighted_by_total_positions() {
+        let report = vec![
+            coverage_of("a.ts".to_string(), &[span(false)]),
+            coverage_of("b.ts".to_string(), &[span(true), span(true), span(true)]),
+        ];
+
+        // 1 typed out of 4 total, not an unweighted average of 100% and 0%.
+        assert_eq!(overall_percent(&report), 25.0);
+    }
+
+    #[test]
+    fn files_below_threshold_filters_by_percent() {
+        let report = vec![
+            coverage_of("good.ts".to_string(), &[span(false)]),
+            coverage_of("bad.ts".to_string(), &[span(true)]),
+        ];
+
+        let below = files_below_threshold(&report, 50.0);
+        assert_eq!(below.len(), 1);
+        assert_eq!(below[0].file, "bad.ts");
+    }
+
+    #[test]
+    fn serializes_with_camel_case_any_count_and_a_percent_field() {
+        let coverage = coverage_of("foo.ts".to_string(), &[span(false), span(true)]);
+        let json = serde_json::to_value(&coverage).unwrap();
+
+        assert_eq!(json["file"], "foo.ts");
+        assert_eq!(json["total"], 2);
+        assert_eq!(json["typed"], 1);
+        assert_eq!(json["anyCount"], 1);
+        assert_eq!(json["percent"], 50.0);
+        assert!(json.get("any_count").is_none());
+    }
+}
@@ -0,0 +1,89 @@
+//! Machine-readable diagnostics.
+//!
+//! The test harness normally prints `Checker`'s errors through
+//! `Handler::struct_span_err` for human consumption. This module provides a `serde`-friendly mirror of
+//! those diagnostics, modeled on the tagged-enum events Deno's `tsc` host
+//! emits (`#[serde(tag = "kind", content = "data")]`), so tooling can consume
+//! a run's output as JSON instead of scraping terminal text.
+
+use serde::Serialize;
+use swc_common::{SourceMap, Spanned};
+
+use crate::Error;
+
+/// A single diagnostic, ready to be serialized to JSON.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", content = "data", rename_all = "camelCase")]
+pub enum Diagnostic {
+    Error(DiagnosticData),
+}
+
+/// The payload shared by every diagnostic kind.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticData {
+    pub file_name: String,
+    pub start: Position,
+    pub end: Position,
+    pub code: Option<u32>,
+    pub category: DiagnosticCategory,
+    pub message_text: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub related_information: Vec<RelatedInformation>,
+}
+
+/// A 1-based line/column pair, resolved through the `SourceMap`.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Position {
+    pub line: usize,
+    pub col: usize,
+}
+
+/// Severity of a diagnostic. `stc` currently only ever reports hard errors,
+/// but the variants mirror `tsc`'s so downstream tooling doesn't need to
+/// special-case us.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiagnosticCategory {
+    Error,
+    Warning,
+    Suggestion,
+    Message,
+}
+
+/// A secondary span referenced by a diagnostic, e.g. the other side of a
+/// conflicting declaration.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RelatedInformation {
+    pub file_name: String,
+    pub start: Position,
+    pub end: Position,
+    pub message_text: String,
+}
+
+impl Diagnostic {
+    /// Converts an internal [`Error`] into its JSON-serializable form,
+    /// resolving spans through `cm`.
+    pub fn from_error(err: &Error, cm: &SourceMap) -> Self {
+        let span = err.span();
+        let loc_lo = cm.lookup_char_pos(span.lo);
+        let loc_hi = cm.lookup_char_pos(span.hi);
+
+        Diagnostic::Error(DiagnosticData {
+            file_name: loc_lo.file.name.to_string(),
+            start: Position {
+                line: loc_lo.line,
+                col: loc_lo.col_display + 1,
+            },
+            end: Position {
+                line: loc_hi.line,
+                col: loc_hi.col_display + 1,
+            },
+            code: crate::error_code(&err.kind),
+            category: DiagnosticCategory::Error,
+            message_text: err.kind.message(),
+            related_information: Vec::new(),
+        })
+    }
+}
@@ -0,0 +1,322 @@
+//! Structural diffing of `.d.ts` modules.
+//!
+//! `test_project` used to fall back to comparing the generated and expected
+//! declaration files as whole-module text, so a single reordered or
+//! differently-formatted member turned into an unreadable wall of diff.
+//! This compares top-level exported declarations by name instead, so a
+//! mismatch is reported per-symbol and is insensitive to declaration order.
+
+use std::collections::HashMap;
+
+use swc_ecma_ast::{Decl, ExportDecl, ExportSpecifier, Module, ModuleDecl, ModuleItem, NamedExport, Pat, Stmt, TsModuleName, VarDecl};
+
+/// A single top-level symbol that differs between two `.d.ts` modules.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemberDiff {
+    pub name: String,
+    pub kind: DiffKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffKind {
+    /// Present in the generated output but not in the expected one.
+    Added,
+    /// Present in the expected output but not in the generated one.
+    Removed,
+    /// Present in both, but with a different rendering.
+    Changed { expected: String, generated: String },
+}
+
+/// Diffs `generated` against `expected`, matching top-level items by a
+/// stable key and rendering each matched group with `render` to compare
+/// their bodies.
+///
+/// Named fn/class/interface/type-alias/enum/namespace declarations, each
+/// declarator of a multi-declarator `var`/`const`, each specifier of a
+/// named export, and default exports are all matched by name. Anything
+/// left over (e.g. `export * from "..."`, an ambient `declare module
+/// "..."` string augmentation) has no stable name to match on order
+/// -insensitively, but is still compared positionally rather than dropped,
+/// so a real mismatch there still fails the comparison.
+///
+/// A name isn't necessarily unique: function overloads and declaration
+/// merging (repeated `interface Foo {}` / `namespace Foo {}`) both produce
+/// several top-level items under the same key. Those are grouped and
+/// compared as a whole, order-insensitively, rather than the last one
+/// silently overwriting the rest.
+pub fn diff(generated: &Module, expected: &Module, render: impl Fn(&ModuleItem) -> String) -> Vec<MemberDiff> {
+    let generated = index(generated);
+    let expected = index(expected);
+
+    let mut keys: Vec<&String> = generated.keys().chain(expected.keys()).collect();
+    keys.sort();
+    keys.dedup();
+
+    keys.into_iter()
+        .filter_map(|key| match (generated.get(key), expected.get(key)) {
+            (Some(_), None) => Some(MemberDiff {
+                name: key.clone(),
+                kind: DiffKind::Added,
+            }),
+            (None, Some(_)) => Some(MemberDiff {
+                name: key.clone(),
+                kind: DiffKind::Removed,
+            }),
+            (Some(g), Some(e)) => {
+                let mut generated: Vec<String> = g.iter().map(&render).collect();
+                let mut expected: Vec<String> = e.iter().map(&render).collect();
+                generated.sort();
+                expected.sort();
+
+                (generated != expected).then_some(MemberDiff {
+                    name: key.clone(),
+                    kind: DiffKind::Changed {
+                        expected: expected.join("\n"),
+                        generated: generated.join("\n"),
+                    },
+                })
+            }
+            (None, None) => unreachable!("key came from one of the two maps"),
+        })
+        .collect()
+}
+
+fn index(module: &Module) -> HashMap<String, Vec<ModuleItem>> {
+    let mut map: HashMap<String, Vec<ModuleItem>> = HashMap::new();
+    for (i, item) in module.body.iter().enumerate() {
+        for (key, item) in expand(item, i) {
+            map.entry(key).or_default().push(item);
+        }
+    }
+    map
+}
+
+/// Splits a single top-level item into `(key, item)` pairs that can each be
+/// matched and rendered independently: a multi-declarator `var`/`const`
+/// becomes one pair per declarator, a named export becomes one pair per
+/// specifier, and everything else is either named directly or, if it has
+/// no stable name, keyed by its position so it's still compared.
+fn expand(item: &ModuleItem, index: usize) -> Vec<(String, ModuleItem)> {
+    match item {
+        ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(export)) if matches!(&export.decl, Decl::Var(v) if v.decls.len() > 1) => {
+            match &export.decl {
+                Decl::Var(var) => var
+                    .decls
+                    .iter()
+                    .map(|d| {
+                        let key = pat_name(&d.name).unwrap_or_else(|| format!("#{}", index));
+                        let item = ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(ExportDecl {
+                            span: export.span,
+                            decl: Decl::Var(Box::new(VarDecl {
+                                decls: vec![d.clone()],
+                                ..(**var).clone()
+                            })),
+                        }));
+                        (key, item)
+                    })
+                    .collect(),
+                _ => unreachable!("guarded by the match arm's `if`"),
+            }
+        }
+        ModuleItem::Stmt(Stmt::Decl(Decl::Var(var))) if var.decls.len() > 1 => var
+            .decls
+            .iter()
+            .map(|d| {
+                let key = pat_name(&d.name).unwrap_or_else(|| format!("#{}", index));
+                let item = ModuleItem::Stmt(Stmt::Decl(Decl::Var(Box::new(VarDecl {
+                    decls: vec![d.clone()],
+                    ..(**var).clone()
+                }))));
+                (key, item)
+            })
+            .collect(),
+        ModuleItem::ModuleDecl(ModuleDecl::ExportDefaultDecl(_)) | ModuleItem::ModuleDecl(ModuleDecl::ExportDefaultExpr(_)) => {
+            vec![("default".to_string(), item.clone())]
+        }
+        ModuleItem::ModuleDecl(ModuleDecl::ExportNamed(named)) if !named.specifiers.is_empty() => named
+            .specifiers
+            .iter()
+            .map(|spec| {
+                let key = export_specifier_name(spec);
+                let item = ModuleItem::ModuleDecl(ModuleDecl::ExportNamed(NamedExport {
+                    specifiers: vec![spec.clone()],
+                    ..named.clone()
+                }));
+                (key, item)
+            })
+            .collect(),
+        _ => {
+            let key = name_of(item).unwrap_or_else(|| format!("#{}", index));
+            vec![(key, item.clone())]
+        }
+    }
+}
+
+/// The stable name a single-declarator declaration is matched by, if it has
+/// one.
+fn name_of(item: &ModuleItem) -> Option<String> {
+    let decl = match item {
+        ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(ExportDecl { decl, .. })) => decl,
+        ModuleItem::Stmt(Stmt::Decl(decl)) => decl,
+        _ => return None,
+    };
+
+    let name = match decl {
+        Decl::Fn(f) => f.ident.sym.to_string(),
+        Decl::Class(c) => c.ident.sym.to_string(),
+        Decl::TsInterface(i) => i.id.sym.to_string(),
+        Decl::TsTypeAlias(t) => t.id.sym.to_string(),
+        Decl::TsEnum(e) => e.id.sym.to_string(),
+        // A named namespace (`namespace Foo { ... }` / `module Foo { ... }`)
+        // has a stable `Ident` name and is diffable like any other
+        // declaration. An ambient string module augmentation
+        // (`declare module "foo" { ... }`) doesn't, and falls through to
+        // the caller's positional fallback instead of being dropped.
+        Decl::TsModule(m) => match &m.id {
+            TsModuleName::Ident(ident) => ident.sym.to_string(),
+            TsModuleName::Str(_) => return None,
+        },
+        Decl::Var(v) => pat_name(&v.decls.first()?.name)?,
+    };
+
+    Some(name)
+}
+
+fn pat_name(pat: &Pat) -> Option<String> {
+    match pat {
+        Pat::Ident(ident) => Some(ident.id.sym.to_string()),
+        _ => None,
+    }
+}
+
+fn export_specifier_name(spec: &ExportSpecifier) -> String {
+    match spec {
+        ExportSpecifier::Named(named) => named.exported.as_ref().unwrap_or(&named.orig).sym.to_string(),
+        ExportSpecifier::Default(default) => default.exported.sym.to_string(),
+        ExportSpecifier::Namespace(ns) => ns.name.sym.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use swc_common::FileName;
+    use swc_ecma_ast::EsVersion;
+    use swc_ecma_parser::{lexer::Lexer, Parser, StringInput, Syntax, TsConfig};
+    use swc_ecma_utils::drop_span;
+
+    use super::*;
+
+    fn parse(src: &str) -> Module {
+        let cm = swc_common::SourceMap::default();
+        let fm = cm.new_source_file(FileName::Anon, src.to_string());
+
+        let lexer = Lexer::new(
+            Syntax::Typescript(TsConfig {
+                dts: true,
+                ..Default::default()
+            }),
+            EsVersion::latest(),
+            StringInput::from(&*fm),
+            None,
+        );
+
+        drop_span(Parser::new_from(lexer).parse_module().unwrap())
+    }
+
+    fn render(item: &ModuleItem) -> String {
+        format!("{:?}", drop_span(item.clone()))
+    }
+
+    #[test]
+    fn identical_modules_have_no_diff() {
+        let m = parse("export function foo(a: string): void;");
+        assert_eq!(diff(&m, &m, render), vec![]);
+    }
+
+    #[test]
+    fn detects_added_symbol() {
+        let generated = parse("export function foo(): void;\nexport function bar(): void;");
+        let expected = parse("export function foo(): void;");
+
+        assert_eq!(
+            diff(&generated, &expected, render),
+            vec![MemberDiff {
+                name: "bar".to_string(),
+                kind: DiffKind::Added,
+            }]
+        );
+    }
+
+    #[test]
+    fn detects_removed_symbol() {
+        let generated = parse("export function foo(): void;");
+        let expected = parse("export function foo(): void;\nexport function bar(): void;");
+
+        assert_eq!(
+            diff(&generated, &expected, render),
+            vec![MemberDiff {
+                name: "bar".to_string(),
+                kind: DiffKind::Removed,
+            }]
+        );
+    }
+
+    #[test]
+    fn detects_changed_symbol() {
+        let generated = parse("export function foo(a: string): void;");
+        let expected = parse("export function foo(a: number): void;");
+
+        let diffs = diff(&generated, &expected, render);
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].name, "foo");
+        assert!(matches!(diffs[0].kind, DiffKind::Changed { .. }));
+    }
+
+    /// Regression test for overwritten overloads: two items sharing a name
+    /// used to collide in a single `HashMap` slot, so only the last-seen
+    /// overload was ever compared.
+    #[test]
+    fn overloads_sharing_a_name_are_compared_as_a_group_not_overwritten() {
+        let generated = parse("export function foo(a: string): void;\nexport function foo(a: number): void;");
+
+        // The same two overloads, reordered: order alone must not be a diff.
+        let reordered = parse("export function foo(a: number): void;\nexport function foo(a: string): void;");
+        assert_eq!(diff(&generated, &reordered, render), vec![]);
+
+        // A real change to the *first* overload must still be caught, even
+        // though both sides have the same name and the same overload count.
+        let changed = parse("export function foo(a: boolean): void;\nexport function foo(a: number): void;");
+        let diffs = diff(&generated, &changed, render);
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].name, "foo");
+    }
+
+    /// Regression test for declaration merging (`namespace Foo {}` repeated)
+    /// hitting the same overwrite bug as function overloads.
+    #[test]
+    fn merged_namespace_declarations_are_compared_as_a_group() {
+        let generated = parse("namespace Foo { export const a: string; }\nnamespace Foo { export const b: number; }");
+        let reordered = parse("namespace Foo { export const b: number; }\nnamespace Foo { export const a: string; }");
+        assert_eq!(diff(&generated, &reordered, render), vec![]);
+    }
+
+    #[test]
+    fn multi_declarator_var_is_diffed_per_declarator() {
+        let generated = parse("export declare const a: string, b: number;");
+        let expected = parse("export declare const a: string, b: boolean;");
+
+        let diffs = diff(&generated, &expected, render);
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].name, "b");
+    }
+
+    #[test]
+    fn ambient_module_string_augmentation_has_no_stable_name() {
+        assert_eq!(name_of(&parse("declare module \"foo\" {}").body[0]), None);
+    }
+
+    #[test]
+    fn named_namespace_has_a_stable_name() {
+        assert_eq!(name_of(&parse("namespace Foo {}").body[0]), Some("Foo".to_string()));
+    }
+}
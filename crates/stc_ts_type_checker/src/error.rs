@@ -0,0 +1,141 @@
+use serde::{Deserialize, Serialize};
+use swc_common::{Span, Spanned};
+
+/// A single type-checking error produced while analyzing a module.
+///
+/// This is intentionally a thin wrapper around [`ErrorKind`] so new
+/// diagnostics can be added without touching every call site that only
+/// cares about the span.
+///
+/// `Error` is serializable so it can be round-tripped through the disk
+/// cache in [`crate::cache`].
+#[derive(Debug, Clone, Spanned, Serialize, Deserialize)]
+pub struct Error {
+    pub span: Span,
+    pub kind: ErrorKind,
+}
+
+/// The distinct kinds of type errors `Checker` can report.
+///
+/// This is a small subset of the diagnostics a full checker would emit, but
+/// it's enough to drive the reporting machinery (JSON diagnostics, TS error
+/// codes, coverage, ...) built on top of it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ErrorKind {
+    /// `Type 'Foo' is not assignable to type 'Bar'`.
+    NotAssignable { left: String, right: String },
+    /// `Property 'foo' does not exist on type 'Bar'`.
+    NoSuchProperty { prop: String, obj: String },
+    /// `Cannot find name 'foo'`.
+    CannotFindName { name: String },
+    /// `Module './foo' has no exported member 'Bar'`.
+    NoSuchExport { module: String, name: String },
+    /// A module could not be resolved at all.
+    ModuleNotFound { specifier: String },
+}
+
+/// Every `ErrorKind` variant name paired with the `tsc` diagnostic code
+/// (`TSxxxx`, here stored without the `TS` prefix) it's meant to be
+/// comparable against. Kept as a table, rather than inlined in
+/// [`error_code`] and [`kind_names_for_code`], so the two stay in sync and
+/// the full mapping can be walked (e.g. to assert stc's code set against
+/// `tsc`'s in the test harness).
+pub const ERROR_CODES: &[(&str, u32)] = &[
+    ("NotAssignable", 2322),
+    ("NoSuchProperty", 2339),
+    ("CannotFindName", 2304),
+    ("NoSuchExport", 2305),
+    ("ModuleNotFound", 2307),
+];
+
+/// Maps an [`ErrorKind`] to the `tsc`-compatible numeric code used to
+/// compare stc's output against real `tsc` runs.
+pub fn error_code(kind: &ErrorKind) -> Option<u32> {
+    let name = match kind {
+        ErrorKind::NotAssignable { .. } => "NotAssignable",
+        ErrorKind::NoSuchProperty { .. } => "NoSuchProperty",
+        ErrorKind::CannotFindName { .. } => "CannotFindName",
+        ErrorKind::NoSuchExport { .. } => "NoSuchExport",
+        ErrorKind::ModuleNotFound { .. } => "ModuleNotFound",
+    };
+
+    ERROR_CODES.iter().find(|(n, _)| *n == name).map(|(_, code)| *code)
+}
+
+/// Reverse lookup: the name(s) of the `ErrorKind` variant(s) that report
+/// `code`. There's a 1:1 mapping today, but this returns a `Vec` since
+/// nothing rules out two variants sharing a `tsc` code later on.
+pub fn kind_names_for_code(code: u32) -> Vec<&'static str> {
+    ERROR_CODES.iter().filter(|(_, c)| *c == code).map(|(name, _)| *name).collect()
+}
+
+impl ErrorKind {
+    /// Renders the human-readable message used by watch mode's report
+    /// ([`crate::watch`]) and by the JSON diagnostics mode
+    /// ([`crate::diagnostics`]).
+    pub fn message(&self) -> String {
+        match self {
+            ErrorKind::NotAssignable { left, right } => {
+                format!("Type '{}' is not assignable to type '{}'.", left, right)
+            }
+            ErrorKind::NoSuchProperty { prop, obj } => {
+                format!("Property '{}' does not exist on type '{}'.", prop, obj)
+            }
+            ErrorKind::CannotFindName { name } => format!("Cannot find name '{}'.", name),
+            ErrorKind::NoSuchExport { module, name } => {
+                format!("Module '{}' has no exported member '{}'.", module, name)
+            }
+            ErrorKind::ModuleNotFound { specifier } => format!("Cannot find module '{}'.", specifier),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_error_kind_has_a_code() {
+        let kind = ErrorKind::CannotFindName { name: "foo".to_string() };
+        assert_eq!(error_code(&kind), Some(2304));
+
+        for kind in [
+            ErrorKind::NotAssignable {
+                left: "Foo".to_string(),
+                right: "Bar".to_string(),
+            },
+            ErrorKind::NoSuchProperty {
+                prop: "foo".to_string(),
+                obj: "Bar".to_string(),
+            },
+            ErrorKind::NoSuchExport {
+                module: "./foo".to_string(),
+                name: "Bar".to_string(),
+            },
+            ErrorKind::ModuleNotFound { specifier: "./foo".to_string() },
+        ] {
+            assert!(error_code(&kind).is_some(), "no code for {:?}", kind);
+        }
+    }
+
+    #[test]
+    fn error_codes_and_kind_names_for_code_agree() {
+        for &(name, code) in ERROR_CODES {
+            assert_eq!(kind_names_for_code(code), vec![name], "ERROR_CODES and kind_names_for_code disagree for {}", name);
+        }
+    }
+
+    #[test]
+    fn kind_names_for_code_is_empty_for_an_unknown_code() {
+        assert!(kind_names_for_code(0).is_empty());
+    }
+
+    #[test]
+    fn message_interpolates_its_fields() {
+        let kind = ErrorKind::NotAssignable {
+            left: "Foo".to_string(),
+            right: "Bar".to_string(),
+        };
+        assert_eq!(kind.message(), "Type 'Foo' is not assignable to type 'Bar'.");
+    }
+}
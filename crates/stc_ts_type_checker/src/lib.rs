@@ -0,0 +1,14 @@
+#![feature(box_syntax)]
+
+pub use self::{
+    checker::{Checker, ModuleId},
+    error::{error_code, kind_names_for_code, Error, ErrorKind, ERROR_CODES},
+};
+
+pub mod cache;
+mod checker;
+pub mod coverage;
+pub mod diagnostics;
+pub mod dts_diff;
+mod error;
+pub mod watch;
@@ -0,0 +1,120 @@
+//! Watch mode: keep a [`Checker`] resident and incrementally recheck only
+//! what a filesystem change could have affected, instead of restarting the
+//! whole check from scratch (like Deno's `file_watcher`).
+
+use std::{
+    path::{Path, PathBuf},
+    sync::{mpsc::channel, Arc},
+    time::Duration,
+};
+
+use notify::{DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+use swc_common::FileName;
+
+use crate::Checker;
+
+pub struct WatchOptions {
+    /// How long to wait for more filesystem events before recomputing, so a
+    /// save-all or a `git checkout` doesn't trigger one recheck per file.
+    pub debounce: Duration,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        WatchOptions {
+            debounce: Duration::from_millis(100),
+        }
+    }
+}
+
+/// Checks `entries` once, then watches `root` and recomputes the affected
+/// set (the changed modules plus their transitive dependents) on every
+/// change, printing diagnostics after each round.
+///
+/// Returns an error if the watcher can't be set up; after that, runs until
+/// the watcher channel is closed. Error events reported by the watcher are
+/// ignored.
+pub fn watch(checker: &mut Checker, root: &Path, entries: Vec<Arc<FileName>>, opts: WatchOptions) -> notify::Result<()> {
+    for entry in &entries {
+        checker.check(entry.clone());
+    }
+    report(checker);
+
+    let (tx, rx) = channel();
+    let mut watcher: RecommendedWatcher = Watcher::new(tx, opts.debounce)?;
+    watcher.watch(root, RecursiveMode::Recursive)?;
+
+    loop {
+        let event = match rx.recv() {
+            Ok(event) => event,
+            Err(_) => return Ok(()),
+        };
+
+        let changed_ids: Vec<_> = changed_paths(event)
+            .into_iter()
+            .filter_map(|path| checker.existing_id(&Arc::new(FileName::Real(path))))
+            .collect();
+
+        if changed_ids.is_empty() {
+            continue;
+        }
+
+        let affected = checker.affected_by(&changed_ids);
+        for &id in &affected {
+            checker.invalidate(id);
+        }
+        for id in affected {
+            let path = checker.path_of(id);
+            checker.check(path);
+        }
+
+        report(checker);
+    }
+}
+
+fn changed_paths(event: DebouncedEvent) -> Vec<PathBuf> {
+    match event {
+        DebouncedEvent::Create(path) | DebouncedEvent::Write(path) | DebouncedEvent::Chmod(path) => vec![path],
+        DebouncedEvent::Rename(from, to) => vec![from, to],
+        DebouncedEvent::Remove(path) => vec![path],
+        _ => vec![],
+    }
+}
+
+fn report(checker: &mut Checker) {
+    for err in checker.take_errors() {
+        eprintln!("{}", err.kind.message());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn create_write_chmod_and_remove_yield_their_single_path() {
+        let path = PathBuf::from("/tmp/foo.ts");
+        for event in [
+            DebouncedEvent::Create(path.clone()),
+            DebouncedEvent::Write(path.clone()),
+            DebouncedEvent::Chmod(path.clone()),
+            DebouncedEvent::Remove(path.clone()),
+        ] {
+            assert_eq!(changed_paths(event), vec![path.clone()]);
+        }
+    }
+
+    #[test]
+    fn rename_yields_both_the_old_and_new_path() {
+        let from = PathBuf::from("/tmp/old.ts");
+        let to = PathBuf::from("/tmp/new.ts");
+
+        assert_eq!(changed_paths(DebouncedEvent::Rename(from.clone(), to.clone())), vec![from, to]);
+    }
+
+    #[test]
+    fn other_events_yield_no_paths() {
+        assert_eq!(changed_paths(DebouncedEvent::Rescan), vec![] as Vec<PathBuf>);
+        assert_eq!(changed_paths(DebouncedEvent::Error(notify::Error::WatchNotFound, None)), vec![] as Vec<PathBuf>);
+    }
+}
@@ -3,18 +3,23 @@
 use std::{
     fs::read_to_string,
     path::{Path, PathBuf},
-    process::{Command, Stdio},
+    process::Command,
     sync::Arc,
 };
 
 use anyhow::{Context, Error};
 use ignore::WalkBuilder;
+use rand::{rngs::SmallRng, seq::SliceRandom, Rng, SeedableRng};
+use rayon::prelude::*;
 use stc_testing::get_git_root;
 use stc_ts_builtin_types::Lib;
 use stc_ts_env::{Env, ModuleConfig};
 use stc_ts_file_analyzer::env::EnvFactory;
 use stc_ts_module_loader::resolvers::node::NodeResolver;
-use stc_ts_type_checker::Checker;
+use stc_ts_type_checker::{
+    dts_diff::{self, DiffKind},
+    Checker,
+};
 use swc_common::{
     errors::{ColorConfig, Handler},
     FileName, SourceMap, Spanned,
@@ -30,8 +35,13 @@ use testing::{assert_eq, NormalizedOutput};
 fn rxjs() -> Result<(), Error> {
     let dir = get_git_root().join("vendor").join("rxjs").join("src").canonicalize().unwrap();
 
-    tsc(&dir.join("index.ts"), &[]).unwrap();
-    test_project("rxjs", &dir, vec![dir.join("index.ts"), dir.join("webSocket").join("index.ts")]);
+    let tsc_codes = tsc(&dir.join("index.ts"), &[]).unwrap();
+    test_project(
+        "rxjs",
+        &dir,
+        vec![dir.join("index.ts"), dir.join("webSocket").join("index.ts")],
+        tsc_codes,
+    );
 
     Ok(())
 }
@@ -47,8 +57,8 @@ fn vite_js() {
         .canonicalize()
         .unwrap();
 
-    tsc(&dir.join("index.ts"), &["--p", "tsconfig.base.json"]).unwrap();
-    test_project("vite", &dir, vec![dir.join("index.ts")]);
+    let tsc_codes = tsc(&dir.join("index.ts"), &["--p", "tsconfig.base.json"]).unwrap();
+    test_project("vite", &dir, vec![dir.join("index.ts")], tsc_codes);
 }
 
 #[test]
@@ -56,12 +66,14 @@ fn vite_js() {
 fn redux() {
     let dir = get_git_root().join("vendor").join("redux").join("src").canonicalize().unwrap();
 
-    tsc(&dir.join("index.ts"), &[]).unwrap();
-    test_project("redux", &dir, vec![dir.join("index.ts")]);
+    let tsc_codes = tsc(&dir.join("index.ts"), &[]).unwrap();
+    test_project("redux", &dir, vec![dir.join("index.ts")], tsc_codes);
 }
 
-/// Invoke tsc
-fn tsc(path: &Path, args: &[&str]) -> anyhow::Result<()> {
+/// Invokes `tsc`, mirroring its stdout/stderr to ours, and returns the set
+/// of `TSxxxx` diagnostic codes it reported so `test_project` can compare
+/// stc's own codes against them.
+fn tsc(path: &Path, args: &[&str]) -> anyhow::Result<Vec<u32>> {
     eprintln!("tsc: {}", path.display());
     let mut c = Command::new(get_git_root().join("node_modules").join(".bin").join("tsc"));
     c.arg(path)
@@ -73,23 +85,44 @@ fn tsc(path: &Path, args: &[&str]) -> anyhow::Result<()> {
         .arg("es2020")
         .arg("--lib")
         .arg("es2020,dom")
-        .args(args)
-        .stdout(Stdio::inherit())
-        .stderr(Stdio::inherit());
-    let status = c.status().context("failed to get output from tsc")?;
+        .args(args);
+    let output = c.output().context("failed to get output from tsc")?;
 
-    dbg!(status);
-    // assert!(status.success());
+    print!("{}", String::from_utf8_lossy(&output.stdout));
+    eprint!("{}", String::from_utf8_lossy(&output.stderr));
+    dbg!(output.status);
+    // assert!(output.status.success());
 
-    Ok(())
+    Ok(tsc_codes_in(&String::from_utf8_lossy(&output.stdout)))
 }
 
-fn test_project(_name: &str, dir: &Path, entries: Vec<PathBuf>) {
+/// Extracts every `TSxxxx` code mentioned in `tsc`'s output.
+fn tsc_codes_in(output: &str) -> Vec<u32> {
+    output
+        .split("TS")
+        .skip(1)
+        .filter_map(|rest| rest.chars().take_while(|c| c.is_ascii_digit()).collect::<String>().parse().ok())
+        .collect()
+}
+
+/// Resolves the order in which entries and discovered files are checked.
+///
+/// Reads `STC_SHUFFLE` so a failing ordering can be reproduced with
+/// `STC_SHUFFLE=<seed> cargo test`; with no seed set, one is drawn from
+/// entropy and printed so it can be pinned down afterwards.
+fn shuffle_seed() -> u64 {
+    match std::env::var("STC_SHUFFLE") {
+        Ok(val) => val.parse().unwrap_or_else(|_| panic!("invalid STC_SHUFFLE seed: {}", val)),
+        Err(_) => SmallRng::from_entropy().gen(),
+    }
+}
+
+fn test_project(_name: &str, dir: &Path, mut entries: Vec<PathBuf>, tsc_codes: Vec<u32>) {
     ::testing::run_test2(false, |cm, _| {
         let handler = Handler::with_tty_emitter(ColorConfig::Always, true, false, Some(cm.clone()));
 
         let handler = Arc::new(handler);
-        let mut checker = Checker::new(
+        let checker = Arc::new(Checker::new(
             cm.clone(),
             handler.clone(),
             Env::simple(
@@ -101,18 +134,42 @@ fn test_project(_name: &str, dir: &Path, entries: Vec<PathBuf>) {
             TsConfig { ..Default::default() },
             None,
             Arc::new(NodeResolver),
-        );
+        ));
 
-        for main in entries {
-            let main = Arc::new(FileName::Real(main));
+        let seed = shuffle_seed();
+        eprintln!("shuffle seed: {}", seed);
+        let mut rng = SmallRng::seed_from_u64(seed);
 
+        entries.shuffle(&mut rng);
+
+        // Entries are checked across a thread pool; `Checker` claims each
+        // module for a single in-flight check and blocks any other caller
+        // on it, so overlapping entries that import the same module (e.g.
+        // rxjs's `index.ts` and `webSocket/index.ts`) join that one check
+        // instead of racing to duplicate it.
+        entries.into_par_iter().for_each(|main| {
+            let main = Arc::new(FileName::Real(main));
             checker.check(main);
-        }
+        });
 
-        for err in checker.take_errors() {
+        let errors = checker.take_errors();
+        let mut stc_codes: Vec<u32> = errors.iter().filter_map(|err| stc_ts_type_checker::error_code(&err.kind)).collect();
+        for err in errors {
             handler.struct_span_err(err.span(), &format!("{:?}", err)).emit();
         }
 
+        // `tsc`'s own diagnostics are advisory (stc doesn't aim for 1:1
+        // coverage yet), so this only runs when explicitly requested.
+        if std::env::var("STC_ASSERT_TSC_CODES").is_ok() {
+            stc_codes.sort_unstable();
+            stc_codes.dedup();
+            let mut tsc_codes = tsc_codes;
+            tsc_codes.sort_unstable();
+            tsc_codes.dedup();
+            assert_eq!(stc_codes, tsc_codes, "stc and tsc reported a different set of diagnostic codes");
+        }
+
+        let mut files = vec![];
         for entry in WalkBuilder::new(dir).git_ignore(false).build() {
             let entry = entry.unwrap();
 
@@ -124,7 +181,12 @@ fn test_project(_name: &str, dir: &Path, entries: Vec<PathBuf>) {
                 continue;
             }
 
-            let file_path = Arc::new(FileName::Real(entry.path().to_path_buf()));
+            files.push(entry.path().to_path_buf());
+        }
+        files.shuffle(&mut rng);
+
+        for path in files {
+            let file_path = Arc::new(FileName::Real(path.clone()));
 
             let id = checker.id(&file_path);
             let dts_module = match checker.take_dts(id) {
@@ -139,7 +201,7 @@ fn test_project(_name: &str, dir: &Path, entries: Vec<PathBuf>) {
             let generated_dts = drop_span(dts_module);
             let expected_dts = parse_dts(
                 &cm,
-                &read_to_string(entry.path().with_extension("d.ts")).unwrap_or_else(|err| {
+                &read_to_string(path.with_extension("d.ts")).unwrap_or_else(|err| {
                     panic!("Failed to read .d.ts file for {}: {}", file_path, err);
                 }),
             );
@@ -147,18 +209,23 @@ fn test_project(_name: &str, dir: &Path, entries: Vec<PathBuf>) {
                 continue;
             }
 
-            let generated = print(&cm, &generated_dts);
-            let expected = print(&cm, &expected_dts);
-
-            if generated == expected {
+            let diffs = dts_diff::diff(&generated_dts, &expected_dts, |item| print(&cm, &wrap(item)).to_string());
+            if diffs.is_empty() {
                 continue;
             }
 
-            println!("---------- Input ----------\n{}", read_to_string(entry.path()).unwrap());
-            println!("---------- Expected ----------\n{}", expected);
-            println!("---------- Generated ----------\n{}", generated);
+            println!("---------- Input ----------\n{}", read_to_string(&path).unwrap());
+            for diff in &diffs {
+                match &diff.kind {
+                    DiffKind::Added => println!("+ {}: only in generated output", diff.name),
+                    DiffKind::Removed => println!("- {}: only in expected output", diff.name),
+                    DiffKind::Changed { expected, generated } => {
+                        println!("~ {}:\n  expected:  {}\n  generated: {}", diff.name, expected, generated)
+                    }
+                }
+            }
 
-            assert_eq!(generated, expected);
+            panic!("{} top-level declaration(s) differ for {}", diffs.len(), file_path);
         }
 
         Ok(())
@@ -191,6 +258,16 @@ fn parse_dts(cm: &SourceMap, src: &str) -> Module {
     drop_span(module)
 }
 
+/// Wraps a single top-level item back into a `Module` so it can be fed to
+/// [`print`] on its own, for [`dts_diff::diff`]'s per-symbol rendering.
+fn wrap(item: &ModuleItem) -> Module {
+    Module {
+        span: swc_common::DUMMY_SP,
+        body: vec![item.clone()],
+        shebang: None,
+    }
+}
+
 fn print(cm: &Arc<SourceMap>, m: &Module) -> NormalizedOutput {
     let mut buf = vec![];
     {